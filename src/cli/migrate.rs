@@ -0,0 +1,89 @@
+use crate::common::sled_utils::LAST_ID_KEY;
+use crate::raft::db::kv_backend::{open_kv_backend, KvBackend, KvBackendType};
+use crate::raft::db::table::{TableDefinition, TABLE_COUNTER_TREE_NAME, TABLE_DEFINITION_TREE_NAME};
+
+/// Arguments for the offline `export`/`import` subcommand: which backend to read from
+/// and which one to write into.
+pub struct MigrateArgs {
+    pub source_type: KvBackendType,
+    pub source_path: String,
+    pub target_type: KvBackendType,
+    pub target_path: String,
+}
+
+/// Copy every registered table (plus its `seq_*` sequence counter, its
+/// `table_counters` item/byte quota counter, and its `ver_*` per-key version tree)
+/// from `source_path` to `target_path`, so an existing datastore can be moved to a
+/// different `KvBackend` (or snapshotted for a backup) without data loss.
+///
+/// This is meant to be run offline, but only the `sled` backend actually enforces
+/// that: `sled::open` takes an exclusive file lock, so it fails fast if a node is
+/// already serving out of that path. `sqlite` (plain `Connection::open` in WAL mode)
+/// and `lmdb` (an environment explicitly designed for concurrent multi-process
+/// access) impose no such restriction, so running this against either of those while
+/// a node is live won't be stopped here — it will race the node's writes and can
+/// produce an inconsistent snapshot. Only run this against a path you know is not
+/// currently being served.
+pub fn run_migrate(args: MigrateArgs) -> anyhow::Result<()> {
+    let source = open_kv_backend(args.source_type, &args.source_path)?;
+    let target = open_kv_backend(args.target_type, &args.target_path)?;
+    let (tables, rows) = migrate(source.as_ref(), target.as_ref())?;
+    log::info!(
+        "migrated {} table(s), {} row(s) from {:?}:{} to {:?}:{}",
+        tables,
+        rows,
+        args.source_type,
+        args.source_path,
+        args.target_type,
+        args.target_path
+    );
+    Ok(())
+}
+
+/// Returns `(tables_copied, rows_copied)`.
+fn migrate(source: &dyn KvBackend, target: &dyn KvBackend) -> anyhow::Result<(usize, usize)> {
+    source.open_tree(TABLE_DEFINITION_TREE_NAME)?;
+    target.open_tree(TABLE_DEFINITION_TREE_NAME)?;
+    source.open_tree(TABLE_COUNTER_TREE_NAME)?;
+    target.open_tree(TABLE_COUNTER_TREE_NAME)?;
+
+    let mut tables_copied = 0usize;
+    let mut rows_copied = 0usize;
+    for (key, value) in source.iter(TABLE_DEFINITION_TREE_NAME)? {
+        let definition = TableDefinition::from_bytes(&value)?;
+        target.insert(TABLE_DEFINITION_TREE_NAME, &key, value.clone())?;
+
+        let table_tree = format!("t_{}", definition.name);
+        target.open_tree(&table_tree)?;
+        for (k, v) in source.iter(&table_tree)? {
+            target.insert(&table_tree, &k, v)?;
+            rows_copied += 1;
+        }
+
+        if definition.sequence_step != 0 {
+            let seq_tree = format!("seq_{}", definition.name);
+            target.open_tree(&seq_tree)?;
+            if let Some(last_id) = source.get(&seq_tree, LAST_ID_KEY)? {
+                target.insert(&seq_tree, LAST_ID_KEY, last_id)?;
+            }
+        }
+
+        // Without this, a replicated table's keys all come back at version 0 on the
+        // target, making every one of them look maximally stale the moment
+        // anti-entropy runs against a node that wasn't migrated.
+        let ver_tree = format!("ver_{}", definition.name);
+        target.open_tree(&ver_tree)?;
+        for (k, v) in source.iter(&ver_tree)? {
+            target.insert(&ver_tree, &k, v)?;
+        }
+
+        // Without this, a table with an item/byte quota silently resets its counter
+        // to zero on the target until someone remembers to run `recount` by hand,
+        // defeating the quota until then.
+        if let Some(counter) = source.get(TABLE_COUNTER_TREE_NAME, definition.name.as_bytes())? {
+            target.insert(TABLE_COUNTER_TREE_NAME, definition.name.as_bytes(), counter)?;
+        }
+        tables_copied += 1;
+    }
+    Ok((tables_copied, rows_copied))
+}
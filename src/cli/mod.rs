@@ -0,0 +1,60 @@
+pub mod migrate;
+pub mod recount;
+
+use clap::Subcommand;
+
+use crate::raft::db::kv_backend::KvBackendType;
+use migrate::{run_migrate, MigrateArgs};
+use recount::{run_recount, RecountArgs};
+
+/// Offline maintenance subcommands, run instead of starting the node.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Copy a table datastore into a different `KvBackend`, e.g. sled -> sqlite, or
+    /// snapshot/restore for backups. Must be run while no node is serving either path.
+    Migrate {
+        #[arg(long, value_enum)]
+        source_type: KvBackendType,
+        #[arg(long)]
+        source_path: String,
+        #[arg(long, value_enum)]
+        target_type: KvBackendType,
+        #[arg(long)]
+        target_path: String,
+    },
+    /// Rebuild item/byte counters for one table (or all of them) by scanning the data,
+    /// in case an interrupted write left them desynced.
+    Recount {
+        #[arg(long, value_enum)]
+        backend_type: KvBackendType,
+        #[arg(long)]
+        path: String,
+        #[arg(long)]
+        table_name: Option<String>,
+    },
+}
+
+pub fn run(command: Command) -> anyhow::Result<()> {
+    match command {
+        Command::Migrate {
+            source_type,
+            source_path,
+            target_type,
+            target_path,
+        } => run_migrate(MigrateArgs {
+            source_type,
+            source_path,
+            target_type,
+            target_path,
+        }),
+        Command::Recount {
+            backend_type,
+            path,
+            table_name,
+        } => run_recount(RecountArgs {
+            backend_type,
+            path,
+            table_name,
+        }),
+    }
+}
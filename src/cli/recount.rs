@@ -0,0 +1,49 @@
+use crate::raft::db::kv_backend::{open_kv_backend, KvBackendType};
+use crate::raft::db::table::{
+    TableCounter, TableDefinition, TABLE_COUNTER_TREE_NAME, TABLE_DEFINITION_TREE_NAME,
+};
+
+/// Arguments for the offline `recount` subcommand: which backend to repair, and
+/// optionally a single table name (all tables if `None`).
+pub struct RecountArgs {
+    pub backend_type: KvBackendType,
+    pub path: String,
+    pub table_name: Option<String>,
+}
+
+/// Rebuild item/byte counters by scanning each table, in case an interrupted write
+/// left them desynced from the data. Meant to be run offline, but that's only
+/// enforced for the `sled` backend (its file lock fails fast if a node is already
+/// serving out of `path`) — `sqlite` and `lmdb` allow concurrent opens and won't stop
+/// this from racing a live node's writes, which can recount against a half-written
+/// state. Only run this against a path you know is not currently being served.
+pub fn run_recount(args: RecountArgs) -> anyhow::Result<()> {
+    let backend = open_kv_backend(args.backend_type, &args.path)?;
+    backend.open_tree(TABLE_DEFINITION_TREE_NAME)?;
+
+    for (_, v) in backend.iter(TABLE_DEFINITION_TREE_NAME)? {
+        let definition = TableDefinition::from_bytes(&v)?;
+        if let Some(only) = &args.table_name {
+            if only != &definition.name {
+                continue;
+            }
+        }
+
+        let table_tree = format!("t_{}", definition.name);
+        let mut counter = TableCounter::default();
+        for (key, value) in backend.iter(&table_tree)? {
+            counter.item_count += 1;
+            counter.byte_size += (key.len() + value.len()) as u64;
+        }
+        let mut bytes = Vec::new();
+        prost::Message::encode(&counter, &mut bytes)?;
+        backend.insert(TABLE_COUNTER_TREE_NAME, definition.name.as_bytes(), bytes)?;
+        log::info!(
+            "recounted table `{}`: {} item(s), {} byte(s)",
+            definition.name,
+            counter.item_count,
+            counter.byte_size
+        );
+    }
+    Ok(())
+}
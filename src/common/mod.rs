@@ -0,0 +1 @@
+pub mod sled_utils;
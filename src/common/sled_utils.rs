@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use crate::raft::db::kv_backend::KvBackend;
+
+pub(crate) const LAST_ID_KEY: &[u8] = b"last_id";
+
+/// Hands out monotonically increasing ids for a table, batching allocations in the
+/// backing store so every call to `next_id` doesn't need a round-trip: `step` ids are
+/// reserved up front and handed out from memory until the batch is exhausted.
+pub struct TableSequence {
+    backend: Arc<dyn KvBackend>,
+    tree_name: String,
+    step: u64,
+    last_id: u64,
+    max_id: u64,
+}
+
+impl TableSequence {
+    pub fn new(backend: Arc<dyn KvBackend>, tree_name: String, step: u64) -> Self {
+        let last_id = Self::load_last_id(backend.as_ref(), &tree_name);
+        Self {
+            backend,
+            tree_name,
+            step,
+            last_id,
+            max_id: last_id,
+        }
+    }
+
+    fn load_last_id(backend: &dyn KvBackend, tree_name: &str) -> u64 {
+        backend
+            .get(tree_name, LAST_ID_KEY)
+            .ok()
+            .flatten()
+            .and_then(|v| v.try_into().ok())
+            .map(u64::from_be_bytes)
+            .unwrap_or_default()
+    }
+
+    pub fn next_id(&mut self) -> anyhow::Result<u64> {
+        if self.last_id >= self.max_id {
+            self.max_id = self.last_id + self.step.max(1);
+            self.backend.insert(
+                &self.tree_name,
+                LAST_ID_KEY,
+                self.max_id.to_be_bytes().to_vec(),
+            )?;
+        }
+        self.last_id += 1;
+        Ok(self.last_id)
+    }
+
+    /// Forcibly set the last handed-out id, e.g. when replaying a write that already
+    /// carried its own sequence id. The next call to `next_id` reserves a fresh batch
+    /// on top of it.
+    pub fn set_table_last_id(&mut self, last_id: u64) -> anyhow::Result<()> {
+        self.last_id = last_id;
+        self.max_id = last_id;
+        self.backend.insert(
+            &self.tree_name,
+            LAST_ID_KEY,
+            last_id.to_be_bytes().to_vec(),
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,220 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use actix::prelude::*;
+use tokio::sync::Notify;
+
+/// Identifies one piece of config the same way the rest of the system does:
+/// data id + group + tenant (namespace).
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct ConfigKey {
+    pub data_id: Arc<String>,
+    pub group: Arc<String>,
+    pub tenant: Arc<String>,
+}
+
+impl ConfigKey {
+    pub fn new(data_id: &str, group: &str, tenant: &str) -> Self {
+        Self {
+            data_id: Arc::new(data_id.to_owned()),
+            group: Arc::new(group.to_owned()),
+            tenant: Arc::new(tenant.to_owned()),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct ConfigEntry {
+    content: String,
+    // Taken from the actor-wide `last_version` counter on every SET, so a key that is
+    // removed and then set again never reuses a version a client may still hold.
+    // `Watch` compares it to tell whether a client's view is stale without diffing
+    // content; a REMOVE drops the entry, which `Watch` reports as a change too.
+    version: u64,
+}
+
+pub struct ConfigActor {
+    cache: HashMap<ConfigKey, ConfigEntry>,
+    /// Parked `Watch` requests, keyed by the exact key they're waiting on. Drained and
+    /// woken from the SET/REMOVE write path.
+    watchers: HashMap<ConfigKey, Vec<Arc<Notify>>>,
+    /// Last version handed out by a SET, across all keys.
+    last_version: u64,
+}
+
+impl ConfigActor {
+    pub fn new() -> Self {
+        Self {
+            cache: Default::default(),
+            watchers: Default::default(),
+            last_version: 0,
+        }
+    }
+
+    fn notify_watchers(&mut self, key: &ConfigKey) {
+        if let Some(parked) = self.watchers.remove(key) {
+            for notify in parked {
+                notify.notify_waiters();
+            }
+        }
+    }
+
+    /// Deregister `notify` from every key in `keys` it was parked under. Called once
+    /// a `Watch` future resolves (by wake or by timeout) so a key that's watched
+    /// repeatedly but rarely written doesn't leak one `Notify` per poll, and so a
+    /// fired key in a multi-key batch doesn't leave the same waiter parked under
+    /// every other key in that batch until each is separately written.
+    fn remove_watcher(&mut self, keys: &[WatchKey], notify: &Arc<Notify>) {
+        for watched in keys {
+            if let Some(parked) = self.watchers.get_mut(&watched.key) {
+                parked.retain(|n| !Arc::ptr_eq(n, notify));
+                if parked.is_empty() {
+                    self.watchers.remove(&watched.key);
+                }
+            }
+        }
+    }
+}
+
+impl Default for ConfigActor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Actor for ConfigActor {
+    type Context = Context<Self>;
+}
+
+#[derive(Message)]
+#[rtype(result = "anyhow::Result<ConfigResult>")]
+pub enum ConfigCmd {
+    GET(ConfigKey),
+    SET(ConfigKey, String),
+    REMOVE(ConfigKey),
+}
+
+pub enum ConfigResult {
+    DATA(String),
+    NULL,
+}
+
+impl Handler<ConfigCmd> for ConfigActor {
+    type Result = anyhow::Result<ConfigResult>;
+
+    fn handle(&mut self, msg: ConfigCmd, _ctx: &mut Self::Context) -> Self::Result {
+        match msg {
+            ConfigCmd::GET(key) => match self.cache.get(&key) {
+                Some(entry) => Ok(ConfigResult::DATA(entry.content.clone())),
+                None => Ok(ConfigResult::NULL),
+            },
+            ConfigCmd::SET(key, content) => {
+                self.last_version += 1;
+                let entry = self.cache.entry(key.clone()).or_default();
+                entry.content = content;
+                entry.version = self.last_version;
+                self.notify_watchers(&key);
+                Ok(ConfigResult::NULL)
+            }
+            ConfigCmd::REMOVE(key) => {
+                self.cache.remove(&key);
+                self.notify_watchers(&key);
+                Ok(ConfigResult::NULL)
+            }
+        }
+    }
+}
+
+/// One entry in a `WatchConfig` request: the key being watched and the version the
+/// client already has (0 if it has never seen this key).
+pub struct WatchKey {
+    pub key: ConfigKey,
+    pub known_version: u64,
+}
+
+/// Long-poll a set of keys: if any entry's version already differs from what the
+/// client supplied, answer immediately with the changed entries; otherwise park until
+/// a write touches one of them or `timeout` elapses.
+#[derive(Message)]
+#[rtype(result = "anyhow::Result<WatchResult>")]
+pub struct WatchConfig {
+    pub keys: Vec<WatchKey>,
+    pub timeout: Duration,
+}
+
+pub struct WatchResult {
+    /// Keys whose content changed, with their new content and version.
+    pub changed: Vec<(ConfigKey, String, u64)>,
+}
+
+impl ConfigActor {
+    fn diff_versions(&self, keys: &[WatchKey]) -> Vec<(ConfigKey, String, u64)> {
+        keys.iter()
+            .filter_map(|w| match self.cache.get(&w.key) {
+                Some(entry) if entry.version != w.known_version => {
+                    Some((w.key.clone(), entry.content.clone(), entry.version))
+                }
+                None if w.known_version != 0 => Some((w.key.clone(), String::new(), 0)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Handler<WatchConfig> for ConfigActor {
+    type Result = ResponseActFuture<Self, anyhow::Result<WatchResult>>;
+
+    fn handle(&mut self, msg: WatchConfig, _ctx: &mut Self::Context) -> Self::Result {
+        let changed = self.diff_versions(&msg.keys);
+        if !changed.is_empty() {
+            return Box::pin(actix::fut::ready(Ok(WatchResult { changed })))
+                as ResponseActFuture<Self, _>;
+        }
+
+        let notify = Arc::new(Notify::new());
+        for watched in &msg.keys {
+            self.watchers
+                .entry(watched.key.clone())
+                .or_default()
+                .push(notify.clone());
+        }
+        let timeout = msg.timeout;
+        let cleanup_notify = notify.clone();
+        let wait = async move {
+            let _ = tokio::time::timeout(timeout, notify.notified()).await;
+        };
+        Box::pin(actix::fut::wrap_future::<_, Self>(wait).map(move |_, act, _ctx| {
+            act.remove_watcher(&msg.keys, &cleanup_notify);
+            Ok(WatchResult { changed: act.diff_versions(&msg.keys) })
+        })) as ResponseActFuture<Self, _>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watch_cleanup_removes_waiter_from_every_key_in_a_batch() {
+        let mut actor = ConfigActor::new();
+        let key_a = ConfigKey::new("a", "DEFAULT_GROUP", "");
+        let key_b = ConfigKey::new("b", "DEFAULT_GROUP", "");
+        let notify = Arc::new(Notify::new());
+        actor.watchers.entry(key_a.clone()).or_default().push(notify.clone());
+        actor.watchers.entry(key_b.clone()).or_default().push(notify.clone());
+
+        let keys = vec![
+            WatchKey {
+                key: key_a.clone(),
+                known_version: 0,
+            },
+            WatchKey {
+                key: key_b.clone(),
+                known_version: 0,
+            },
+        ];
+        actor.remove_watcher(&keys, &notify);
+
+        assert!(actor.watchers.get(&key_a).is_none());
+        assert!(actor.watchers.get(&key_b).is_none());
+    }
+}
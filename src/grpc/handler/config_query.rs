@@ -1,16 +1,19 @@
 #![allow(unused_imports)]
 
-use crate::{grpc::{PayloadHandler, api_model::{ConfigPublishRequest, BaseResponse, ConfigQueryRequest, ConfigQueryResponse, SUCCESS_CODE, ERROR_CODE}, nacos_proto::Payload, PayloadUtils}, config::config::{ConfigActor, ConfigCmd, ConfigKey, ConfigResult}};
+use std::{sync::Arc, time::Instant};
+
+use crate::{grpc::{PayloadHandler, api_model::{ConfigPublishRequest, BaseResponse, ConfigQueryRequest, ConfigQueryResponse, SUCCESS_CODE, ERROR_CODE}, nacos_proto::Payload, PayloadUtils}, config::config::{ConfigActor, ConfigCmd, ConfigKey, ConfigResult}, metrics::Metrics};
 use actix::prelude::Addr;
 use async_trait::async_trait;
 
 pub struct ConfigQueryRequestHandler{
     config_addr: Addr<ConfigActor>,
+    metrics: Arc<Metrics>,
 }
 
 impl ConfigQueryRequestHandler {
-    pub fn new(config_addr: Addr<ConfigActor>) -> Self {
-        Self { config_addr }
+    pub fn new(config_addr: Addr<ConfigActor>, metrics: Arc<Metrics>) -> Self {
+        Self { config_addr, metrics }
     }
 }
 
@@ -21,6 +24,8 @@ impl PayloadHandler for ConfigQueryRequestHandler {
         let request:ConfigQueryRequest = serde_json::from_slice(&body_vec)?;
         let cmd = ConfigCmd::GET(ConfigKey::new(&request.data_id,&request.group,&request.tenant));
         let mut response = ConfigQueryResponse::default();
+        let start = Instant::now();
+        let mut hit = false;
         match self.config_addr.send(cmd).await{
             Ok(res) => {
                 //let res:ConfigResult = res.unwrap();
@@ -28,6 +33,7 @@ impl PayloadHandler for ConfigQueryRequestHandler {
                 match r {
                     ConfigResult::DATA(content) => {
                         //v.to_owned()
+                        hit = true;
                         response.result_code = SUCCESS_CODE;
                         response.content = content;
                         response.tag = request.tag;
@@ -45,6 +51,7 @@ impl PayloadHandler for ConfigQueryRequestHandler {
                 response.message = Some(err.to_string());
             }
         };
+        self.metrics.record_config_query(hit, start.elapsed());
         Ok(PayloadUtils::build_payload("ConfigQueryResponse", serde_json::to_string(&response)?))
     }
 }
\ No newline at end of file
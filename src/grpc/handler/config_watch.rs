@@ -0,0 +1,88 @@
+#![allow(unused_imports)]
+
+use std::time::Duration;
+
+use crate::{
+    config::config::{ConfigActor, ConfigKey, WatchConfig, WatchKey},
+    grpc::{
+        api_model::{
+            BaseResponse, ConfigBatchListenRequest, ConfigChangeBatchListenResponse, ERROR_CODE,
+            SUCCESS_CODE,
+        },
+        nacos_proto::Payload,
+        PayloadHandler, PayloadUtils,
+    },
+};
+use actix::prelude::Addr;
+use async_trait::async_trait;
+
+/// Default long-poll timeout when the client doesn't ask for a specific one. Mirrors
+/// the 30s most Nacos SDKs use for config long polling.
+const DEFAULT_WATCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Long-poll handler for config change subscriptions. A client registers the keys it
+/// cares about along with the version it already has; we park the request until
+/// one of them changes or the timeout elapses, instead of making the client poll GET
+/// on a tight loop.
+pub struct ConfigWatchRequestHandler {
+    config_addr: Addr<ConfigActor>,
+}
+
+impl ConfigWatchRequestHandler {
+    pub fn new(config_addr: Addr<ConfigActor>) -> Self {
+        Self { config_addr }
+    }
+}
+
+#[async_trait]
+impl PayloadHandler for ConfigWatchRequestHandler {
+    async fn handle(
+        &self,
+        request_payload: Payload,
+        _request_meta: crate::grpc::RequestMeta,
+    ) -> anyhow::Result<Payload> {
+        let body_vec = request_payload.body.unwrap_or_default().value;
+        let request: ConfigBatchListenRequest = serde_json::from_slice(&body_vec)?;
+
+        let keys = request
+            .listen
+            .into_iter()
+            .map(|item| WatchKey {
+                key: ConfigKey::new(&item.data_id, &item.group, &item.tenant),
+                known_version: item.known_version,
+            })
+            .collect();
+        let timeout = request
+            .timeout_ms
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_WATCH_TIMEOUT);
+
+        let mut response = ConfigChangeBatchListenResponse::default();
+        match self.config_addr.send(WatchConfig { keys, timeout }).await {
+            Ok(Ok(result)) => {
+                response.result_code = SUCCESS_CODE;
+                response.changed = result
+                    .changed
+                    .into_iter()
+                    .map(|(key, content, version)| {
+                        (key.data_id.to_string(), key.group.to_string(), content, version)
+                    })
+                    .collect();
+            }
+            Ok(Err(err)) => {
+                response.result_code = ERROR_CODE;
+                response.error_code = ERROR_CODE;
+                response.message = Some(err.to_string());
+            }
+            Err(err) => {
+                response.result_code = ERROR_CODE;
+                response.error_code = ERROR_CODE;
+                response.message = Some(err.to_string());
+            }
+        };
+        Ok(PayloadUtils::build_payload(
+            "ConfigChangeBatchListenResponse",
+            serde_json::to_string(&response)?,
+        ))
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+/// Upper bound (in seconds) of each latency bucket. The handlers these back run in
+/// the sub-millisecond to low-hundreds-of-millisecond range, so the buckets are
+/// concentrated there rather than following the default Prometheus client ladder.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0,
+];
+
+const TABLE_OPS: &[&str] = &["insert", "remove", "next_id", "drop"];
+
+struct Histogram {
+    // Cumulative per-bucket counts, one per `LATENCY_BUCKETS` entry plus an implicit
+    // `+Inf` bucket equal to `count`.
+    bucket_counts: Vec<AtomicU64>,
+    sum_micros: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            bucket_counts: LATENCY_BUCKETS.iter().map(|_| AtomicU64::new(0)).collect(),
+            sum_micros: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    fn observe(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        for (bound, bucket) in LATENCY_BUCKETS.iter().zip(&self.bucket_counts) {
+            if secs <= *bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.sum_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn write(&self, out: &mut String, name: &str, labels: &[(&str, &str)]) {
+        let count = self.count.load(Ordering::Relaxed);
+        for (bound, bucket) in LATENCY_BUCKETS.iter().zip(&self.bucket_counts) {
+            write_metric_line(
+                out,
+                &format!("{name}_bucket"),
+                &with_label(labels, "le", &bound.to_string()),
+                bucket.load(Ordering::Relaxed),
+            );
+        }
+        write_metric_line(
+            out,
+            &format!("{name}_bucket"),
+            &with_label(labels, "le", "+Inf"),
+            count,
+        );
+        write_metric_line(
+            out,
+            &format!("{name}_sum"),
+            labels,
+            self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+        );
+        write_metric_line(out, &format!("{name}_count"), labels, count);
+    }
+}
+
+fn with_label<'a>(labels: &[(&'a str, &'a str)], key: &'a str, value: &'a str) -> Vec<(&'a str, &'a str)> {
+    let mut labels = labels.to_vec();
+    labels.push((key, value));
+    labels
+}
+
+fn format_labels(labels: &[(&str, &str)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let inner = labels
+        .iter()
+        .map(|(k, v)| format!("{k}=\"{v}\""))
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("{{{inner}}}")
+}
+
+fn write_metric_line(out: &mut String, name: &str, labels: &[(&str, &str)], value: impl std::fmt::Display) {
+    out.push_str(name);
+    out.push_str(&format_labels(labels));
+    out.push(' ');
+    out.push_str(&value.to_string());
+    out.push('\n');
+}
+
+struct TableOpMetrics {
+    latency: Histogram,
+    table_counts: Mutex<HashMap<String, u64>>,
+}
+
+impl TableOpMetrics {
+    fn new() -> Self {
+        Self {
+            latency: Histogram::new(),
+            table_counts: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// Process-wide metrics registry for config queries and table operations, rendered
+/// as Prometheus text exposition format by [`crate::metrics::server::serve`].
+pub struct Metrics {
+    config_query_total: AtomicU64,
+    config_query_hit: AtomicU64,
+    config_query_miss: AtomicU64,
+    config_query_latency: Histogram,
+    table_ops: HashMap<&'static str, TableOpMetrics>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            config_query_total: AtomicU64::new(0),
+            config_query_hit: AtomicU64::new(0),
+            config_query_miss: AtomicU64::new(0),
+            config_query_latency: Histogram::new(),
+            table_ops: TABLE_OPS.iter().map(|&op| (op, TableOpMetrics::new())).collect(),
+        }
+    }
+
+    pub fn record_config_query(&self, hit: bool, elapsed: Duration) {
+        self.config_query_total.fetch_add(1, Ordering::Relaxed);
+        if hit {
+            self.config_query_hit.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.config_query_miss.fetch_add(1, Ordering::Relaxed);
+        }
+        self.config_query_latency.observe(elapsed);
+    }
+
+    /// Record one `op` on `table_name`. `op` must be one of [`TABLE_OPS`]; anything
+    /// else is silently dropped rather than growing the metric set at runtime.
+    pub fn record_table_op(&self, op: &'static str, table_name: &str, elapsed: Duration) {
+        let Some(stats) = self.table_ops.get(op) else {
+            return;
+        };
+        stats.latency.observe(elapsed);
+        let mut counts = stats.table_counts.lock().unwrap();
+        *counts.entry(table_name.to_owned()).or_insert(0) += 1;
+    }
+
+    /// Render the full registry in Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP r_nacos_config_query_total Total config query requests handled.\n");
+        out.push_str("# TYPE r_nacos_config_query_total counter\n");
+        write_metric_line(
+            &mut out,
+            "r_nacos_config_query_total",
+            &[],
+            self.config_query_total.load(Ordering::Relaxed),
+        );
+
+        out.push_str("# HELP r_nacos_config_query_hit_total Config query requests that found a value.\n");
+        out.push_str("# TYPE r_nacos_config_query_hit_total counter\n");
+        write_metric_line(
+            &mut out,
+            "r_nacos_config_query_hit_total",
+            &[],
+            self.config_query_hit.load(Ordering::Relaxed),
+        );
+
+        out.push_str("# HELP r_nacos_config_query_miss_total Config query requests that found nothing.\n");
+        out.push_str("# TYPE r_nacos_config_query_miss_total counter\n");
+        write_metric_line(
+            &mut out,
+            "r_nacos_config_query_miss_total",
+            &[],
+            self.config_query_miss.load(Ordering::Relaxed),
+        );
+
+        out.push_str("# HELP r_nacos_config_query_duration_seconds Config query latency.\n");
+        out.push_str("# TYPE r_nacos_config_query_duration_seconds histogram\n");
+        self.config_query_latency
+            .write(&mut out, "r_nacos_config_query_duration_seconds", &[]);
+
+        out.push_str("# HELP r_nacos_table_op_duration_seconds Table operation latency, by op.\n");
+        out.push_str("# TYPE r_nacos_table_op_duration_seconds histogram\n");
+        for (op, stats) in &self.table_ops {
+            stats
+                .latency
+                .write(&mut out, "r_nacos_table_op_duration_seconds", &[("op", op)]);
+        }
+
+        out.push_str("# HELP r_nacos_table_op_total Table operations performed, by op and table.\n");
+        out.push_str("# TYPE r_nacos_table_op_total counter\n");
+        for (op, stats) in &self.table_ops {
+            let counts = stats.table_counts.lock().unwrap();
+            for (table, count) in counts.iter() {
+                write_metric_line(
+                    &mut out,
+                    "r_nacos_table_op_total",
+                    &[("op", op), ("table", table)],
+                    *count,
+                );
+            }
+        }
+
+        out
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+/// Abstraction over the on-disk key/value store backing [`TableManage`](super::table::TableManage).
+///
+/// Everything `TableManage` used to do directly against `sled::Db` (open a tree,
+/// insert/remove a key, iterate, drop a tree, ...) is expressed here instead, so the
+/// actor/command layer never has to know which engine is actually storing the bytes.
+/// `sled` remains the default, but some deployments would rather trade its disk/RAM
+/// footprint and O(n) `.len()` for sqlite or LMDB, and this trait is what lets the
+/// engine be picked from config without touching `TableManage` itself.
+pub trait KvBackend: Send + Sync {
+    /// Make sure the named tree exists, creating it if this is the first use.
+    fn open_tree(&self, tree: &str) -> anyhow::Result<()>;
+
+    fn insert(&self, tree: &str, key: &[u8], value: Vec<u8>) -> anyhow::Result<Option<Vec<u8>>>;
+
+    fn remove(&self, tree: &str, key: &[u8]) -> anyhow::Result<Option<Vec<u8>>>;
+
+    fn get(&self, tree: &str, key: &[u8]) -> anyhow::Result<Option<Vec<u8>>>;
+
+    /// Iterate every key/value pair in `tree`, ordered by key.
+    fn iter(&self, tree: &str) -> anyhow::Result<Box<dyn Iterator<Item = (Vec<u8>, Vec<u8>)>>>;
+
+    fn drop_tree(&self, tree: &str) -> anyhow::Result<()>;
+
+    /// Atomically replace the value at `key` with the result of `f`, which receives the
+    /// current value (if any). Returning `None` from `f` removes the key.
+    fn update(
+        &self,
+        tree: &str,
+        key: &[u8],
+        f: Box<dyn Fn(Option<&[u8]>) -> Option<Vec<u8>> + Send + Sync>,
+    ) -> anyhow::Result<Option<Vec<u8>>>;
+
+    /// Scan `tree` in key order between `start` (inclusive) and `end` (exclusive),
+    /// returning at most `limit` pairs. When `reverse` is set the scan walks backwards
+    /// from `end` towards `start`. Backed by each engine's native ordered scan so
+    /// prefix/range queries don't need to load the whole tree into memory.
+    fn range(
+        &self,
+        tree: &str,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        limit: usize,
+        reverse: bool,
+    ) -> anyhow::Result<Vec<(Vec<u8>, Vec<u8>)>>;
+}
+
+/// Which [`KvBackend`] implementation to open, selected from config.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum KvBackendType {
+    Sled,
+    Sqlite,
+    Lmdb,
+}
+
+impl Default for KvBackendType {
+    fn default() -> Self {
+        Self::Sled
+    }
+}
+
+impl std::str::FromStr for KvBackendType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "sled" => Ok(Self::Sled),
+            "sqlite" => Ok(Self::Sqlite),
+            "lmdb" => Ok(Self::Lmdb),
+            _ => Err(anyhow::anyhow!("unknown kv backend: {}", s)),
+        }
+    }
+}
+
+/// Open the configured backend at `path`.
+pub fn open_kv_backend(kind: KvBackendType, path: &str) -> anyhow::Result<Arc<dyn KvBackend>> {
+    match kind {
+        KvBackendType::Sled => {
+            let db = sled::open(path)?;
+            Ok(Arc::new(SledBackend::new(Arc::new(db))))
+        }
+        KvBackendType::Sqlite => Ok(Arc::new(super::kv_backend_sqlite::SqliteBackend::open(
+            path,
+        )?)),
+        KvBackendType::Lmdb => Ok(Arc::new(super::kv_backend_lmdb::LmdbBackend::open(path)?)),
+    }
+}
+
+/// The original engine, wrapping an already-open `sled::Db` behind [`KvBackend`].
+pub struct SledBackend {
+    db: Arc<sled::Db>,
+}
+
+impl SledBackend {
+    pub fn new(db: Arc<sled::Db>) -> Self {
+        Self { db }
+    }
+
+    fn tree(&self, name: &str) -> anyhow::Result<sled::Tree> {
+        Ok(self.db.open_tree(name)?)
+    }
+}
+
+impl KvBackend for SledBackend {
+    fn open_tree(&self, tree: &str) -> anyhow::Result<()> {
+        self.tree(tree)?;
+        Ok(())
+    }
+
+    fn insert(&self, tree: &str, key: &[u8], value: Vec<u8>) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self.tree(tree)?.insert(key, value)?.map(|v| v.to_vec()))
+    }
+
+    fn remove(&self, tree: &str, key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self.tree(tree)?.remove(key)?.map(|v| v.to_vec()))
+    }
+
+    fn get(&self, tree: &str, key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self.tree(tree)?.get(key)?.map(|v| v.to_vec()))
+    }
+
+    fn iter(&self, tree: &str) -> anyhow::Result<Box<dyn Iterator<Item = (Vec<u8>, Vec<u8>)>>> {
+        let iter = self
+            .tree(tree)?
+            .iter()
+            .filter_map(|r| r.ok().map(|(k, v)| (k.to_vec(), v.to_vec())));
+        Ok(Box::new(iter))
+    }
+
+    fn drop_tree(&self, tree: &str) -> anyhow::Result<()> {
+        self.db.drop_tree(tree)?;
+        Ok(())
+    }
+
+    fn update(
+        &self,
+        tree: &str,
+        key: &[u8],
+        f: Box<dyn Fn(Option<&[u8]>) -> Option<Vec<u8>> + Send + Sync>,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let tree = self.tree(tree)?;
+        let new_value = tree.update_and_fetch(key, move |old| f(old).map(sled::IVec::from))?;
+        Ok(new_value.map(|v| v.to_vec()))
+    }
+
+    fn range(
+        &self,
+        tree: &str,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        limit: usize,
+        reverse: bool,
+    ) -> anyhow::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        use std::ops::Bound;
+        let tree = self.tree(tree)?;
+        let bounds = (
+            start.map(|s| Bound::Included(s.to_vec())).unwrap_or(Bound::Unbounded),
+            end.map(|e| Bound::Excluded(e.to_vec())).unwrap_or(Bound::Unbounded),
+        );
+        let range = tree.range::<Vec<u8>, _>(bounds);
+        let items = if reverse {
+            range
+                .rev()
+                .filter_map(|r| r.ok().map(|(k, v)| (k.to_vec(), v.to_vec())))
+                .take(limit)
+                .collect()
+        } else {
+            range
+                .filter_map(|r| r.ok().map(|(k, v)| (k.to_vec(), v.to_vec())))
+                .take(limit)
+                .collect()
+        };
+        Ok(items)
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::RwLock;
+
+use heed::types::ByteSlice;
+use heed::{Database, Env, EnvOpenOptions};
+
+use super::kv_backend::KvBackend;
+
+/// An LMDB-backed [`KvBackend`] (via the `heed` bindings). Each tree is a named LMDB
+/// sub-database within a single shared environment, which gives operators an
+/// mmap-backed alternative to sled with more predictable memory use.
+pub struct LmdbBackend {
+    env: Env,
+    // heed databases are cheap handles but still need a write txn to create, so we
+    // cache the ones we've already opened/created.
+    trees: RwLock<HashMap<String, Database<ByteSlice, ByteSlice>>>,
+}
+
+impl LmdbBackend {
+    pub fn open(path: &str) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(path)?;
+        let env = EnvOpenOptions::new()
+            .map_size(10 * 1024 * 1024 * 1024) // 10GiB virtual address space, LMDB only uses what's written
+            .max_dbs(256)
+            .open(Path::new(path))?;
+        Ok(Self {
+            env,
+            trees: Default::default(),
+        })
+    }
+
+    fn tree(&self, name: &str) -> anyhow::Result<Database<ByteSlice, ByteSlice>> {
+        if let Some(db) = self.trees.read().unwrap().get(name) {
+            return Ok(*db);
+        }
+        let mut wtxn = self.env.write_txn()?;
+        let db: Database<ByteSlice, ByteSlice> = self.env.create_database(&mut wtxn, Some(name))?;
+        wtxn.commit()?;
+        self.trees.write().unwrap().insert(name.to_owned(), db);
+        Ok(db)
+    }
+}
+
+impl KvBackend for LmdbBackend {
+    fn open_tree(&self, tree: &str) -> anyhow::Result<()> {
+        self.tree(tree)?;
+        Ok(())
+    }
+
+    fn insert(&self, tree: &str, key: &[u8], value: Vec<u8>) -> anyhow::Result<Option<Vec<u8>>> {
+        let db = self.tree(tree)?;
+        let mut wtxn = self.env.write_txn()?;
+        let old = db.get(&wtxn, key)?.map(|v| v.to_vec());
+        db.put(&mut wtxn, key, &value)?;
+        wtxn.commit()?;
+        Ok(old)
+    }
+
+    fn remove(&self, tree: &str, key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        let db = self.tree(tree)?;
+        let mut wtxn = self.env.write_txn()?;
+        let old = db.get(&wtxn, key)?.map(|v| v.to_vec());
+        db.delete(&mut wtxn, key)?;
+        wtxn.commit()?;
+        Ok(old)
+    }
+
+    fn get(&self, tree: &str, key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        let db = self.tree(tree)?;
+        let rtxn = self.env.read_txn()?;
+        Ok(db.get(&rtxn, key)?.map(|v| v.to_vec()))
+    }
+
+    fn iter(&self, tree: &str) -> anyhow::Result<Box<dyn Iterator<Item = (Vec<u8>, Vec<u8>)>>> {
+        let db = self.tree(tree)?;
+        let rtxn = self.env.read_txn()?;
+        let items: Vec<(Vec<u8>, Vec<u8>)> = db
+            .iter(&rtxn)?
+            .filter_map(|r| r.ok().map(|(k, v)| (k.to_vec(), v.to_vec())))
+            .collect();
+        Ok(Box::new(items.into_iter()))
+    }
+
+    fn drop_tree(&self, tree: &str) -> anyhow::Result<()> {
+        let db = self.tree(tree)?;
+        let mut wtxn = self.env.write_txn()?;
+        // `clear` only empties the database; it keeps its dbi slot registered in the
+        // environment, so repeated create/drop cycles would eventually exhaust
+        // `max_dbs`. `delete` actually unregisters it (`mdb_drop(..., del=1)`), freeing
+        // the slot for reuse. It's unsafe because any other `Database` handle still
+        // pointing at this dbi becomes invalid; we immediately drop ours from the
+        // cache below, and `tree()` only ever hands out handles from that cache, so no
+        // stale handle can be reused afterwards.
+        unsafe {
+            db.delete(&mut wtxn)?;
+        }
+        wtxn.commit()?;
+        self.trees.write().unwrap().remove(tree);
+        Ok(())
+    }
+
+    fn update(
+        &self,
+        tree: &str,
+        key: &[u8],
+        f: Box<dyn Fn(Option<&[u8]>) -> Option<Vec<u8>> + Send + Sync>,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let db = self.tree(tree)?;
+        let mut wtxn = self.env.write_txn()?;
+        let old = db.get(&wtxn, key)?.map(|v| v.to_vec());
+        let new_value = f(old.as_deref());
+        match &new_value {
+            Some(v) => db.put(&mut wtxn, key, v)?,
+            None => {
+                db.delete(&mut wtxn, key)?;
+            }
+        };
+        wtxn.commit()?;
+        Ok(new_value)
+    }
+
+    fn range(
+        &self,
+        tree: &str,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        limit: usize,
+        reverse: bool,
+    ) -> anyhow::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        use std::ops::Bound;
+        let db = self.tree(tree)?;
+        let rtxn = self.env.read_txn()?;
+        let bounds = (
+            start.map(Bound::Included).unwrap_or(Bound::Unbounded),
+            end.map(Bound::Excluded).unwrap_or(Bound::Unbounded),
+        );
+        let range = db.range(&rtxn, &bounds)?;
+        let items: Vec<(Vec<u8>, Vec<u8>)> = if reverse {
+            range
+                .rev()
+                .filter_map(|r| r.ok().map(|(k, v)| (k.to_vec(), v.to_vec())))
+                .take(limit)
+                .collect()
+        } else {
+            range
+                .filter_map(|r| r.ok().map(|(k, v)| (k.to_vec(), v.to_vec())))
+                .take(limit)
+                .collect()
+        };
+        Ok(items)
+    }
+}
@@ -0,0 +1,173 @@
+use std::sync::Mutex;
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::kv_backend::KvBackend;
+
+/// A sqlite-backed [`KvBackend`]. Every tree is a sqlite table named after it, with a
+/// `(key BLOB PRIMARY KEY, value BLOB)` schema, so operators who find sled's disk/RAM
+/// footprint too large for their deployment can point `TableManage` at a single sqlite
+/// file instead.
+pub struct SqliteBackend {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteBackend {
+    pub fn open(path: &str) -> anyhow::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn quoted(tree: &str) -> String {
+        // tree names come from our own code (table/tree definitions), not user input,
+        // but quote defensively so an unexpected name can't break the statement.
+        format!("\"{}\"", tree.replace('"', "\"\""))
+    }
+}
+
+impl KvBackend for SqliteBackend {
+    fn open_tree(&self, tree: &str) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (key BLOB PRIMARY KEY, value BLOB NOT NULL)",
+                Self::quoted(tree)
+            ),
+            [],
+        )?;
+        Ok(())
+    }
+
+    fn insert(&self, tree: &str, key: &[u8], value: Vec<u8>) -> anyhow::Result<Option<Vec<u8>>> {
+        self.open_tree(tree)?;
+        let conn = self.conn.lock().unwrap();
+        let old: Option<Vec<u8>> = conn
+            .query_row(
+                &format!("SELECT value FROM {} WHERE key = ?1", Self::quoted(tree)),
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        conn.execute(
+            &format!(
+                "INSERT INTO {} (key, value) VALUES (?1, ?2) \
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+                Self::quoted(tree)
+            ),
+            params![key, value],
+        )?;
+        Ok(old)
+    }
+
+    fn remove(&self, tree: &str, key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        self.open_tree(tree)?;
+        let conn = self.conn.lock().unwrap();
+        let old: Option<Vec<u8>> = conn
+            .query_row(
+                &format!("SELECT value FROM {} WHERE key = ?1", Self::quoted(tree)),
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        conn.execute(
+            &format!("DELETE FROM {} WHERE key = ?1", Self::quoted(tree)),
+            params![key],
+        )?;
+        Ok(old)
+    }
+
+    fn get(&self, tree: &str, key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        self.open_tree(tree)?;
+        let conn = self.conn.lock().unwrap();
+        let value: Option<Vec<u8>> = conn
+            .query_row(
+                &format!("SELECT value FROM {} WHERE key = ?1", Self::quoted(tree)),
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(value)
+    }
+
+    fn iter(&self, tree: &str) -> anyhow::Result<Box<dyn Iterator<Item = (Vec<u8>, Vec<u8>)>>> {
+        self.open_tree(tree)?;
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT key, value FROM {} ORDER BY key ASC",
+            Self::quoted(tree)
+        ))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, Vec<u8>>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Box::new(rows.into_iter()))
+    }
+
+    fn drop_tree(&self, tree: &str) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(&format!("DROP TABLE IF EXISTS {}", Self::quoted(tree)), [])?;
+        Ok(())
+    }
+
+    fn update(
+        &self,
+        tree: &str,
+        key: &[u8],
+        f: Box<dyn Fn(Option<&[u8]>) -> Option<Vec<u8>> + Send + Sync>,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        self.open_tree(tree)?;
+        let conn = self.conn.lock().unwrap();
+        let old: Option<Vec<u8>> = conn
+            .query_row(
+                &format!("SELECT value FROM {} WHERE key = ?1", Self::quoted(tree)),
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let new_value = f(old.as_deref());
+        match &new_value {
+            Some(v) => conn.execute(
+                &format!(
+                    "INSERT INTO {} (key, value) VALUES (?1, ?2) \
+                     ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+                    Self::quoted(tree)
+                ),
+                params![key, v],
+            )?,
+            None => conn.execute(
+                &format!("DELETE FROM {} WHERE key = ?1", Self::quoted(tree)),
+                params![key],
+            )?,
+        };
+        Ok(new_value)
+    }
+
+    fn range(
+        &self,
+        tree: &str,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        limit: usize,
+        reverse: bool,
+    ) -> anyhow::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.open_tree(tree)?;
+        let conn = self.conn.lock().unwrap();
+        let order = if reverse { "DESC" } else { "ASC" };
+        let sql = format!(
+            "SELECT key, value FROM {} \
+             WHERE (?1 IS NULL OR key >= ?1) AND (?2 IS NULL OR key < ?2) \
+             ORDER BY key {} LIMIT ?3",
+            Self::quoted(tree),
+            order
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(params![start, end, limit as i64], |row| {
+                Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, Vec<u8>>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+}
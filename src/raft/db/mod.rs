@@ -0,0 +1,5 @@
+pub mod kv_backend;
+pub mod kv_backend_lmdb;
+pub mod kv_backend_sqlite;
+pub mod replication;
+pub mod table;
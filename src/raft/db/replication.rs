@@ -0,0 +1,381 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use actix::prelude::Addr;
+use async_trait::async_trait;
+use futures::future::join_all;
+
+use super::table::{TableManage, TableManageCmd, TableManageResult};
+
+/// Static id of a node in the cluster. Membership (the `ring`) is assumed to be
+/// supplied from outside (e.g. the existing raft membership list) rather than
+/// maintained here.
+pub type NodeId = u64;
+
+/// Replication factor plus the write/read quorum sizes required of it. `write_quorum`
+/// and `read_quorum` must each be `<= replication_factor`; together
+/// `write_quorum + read_quorum > replication_factor` gives the usual overlap
+/// guarantee that a read always sees the latest acknowledged write.
+#[derive(Clone, Copy, Debug)]
+pub struct ReplicationConfig {
+    pub replication_factor: usize,
+    pub write_quorum: usize,
+    pub read_quorum: usize,
+}
+
+/// One write to replicate: enough to apply it on any node and to arbitrate between
+/// diverging copies by `seq_id`, a per-key version tracked via
+/// `TableManage::insert_versioned`/`get_versioned` (separate from the table's shared
+/// `last_seq_id` id sequence, which isn't a per-key version at all).
+#[derive(Clone)]
+pub struct ReplicatedEntry {
+    pub table_name: Arc<String>,
+    pub key: Vec<u8>,
+    pub value: Vec<u8>,
+    pub seq_id: u64,
+}
+
+/// A key's value and the seq id it was written with, as read back from a node.
+pub struct VersionedValue {
+    pub value: Vec<u8>,
+    pub seq_id: u64,
+}
+
+/// Transport to the rest of the cluster. `TableManage` itself stays single-node; this
+/// is the seam a gRPC (or whatever) client plugs into so the coordinator can reach
+/// peer nodes without `TableManage` knowing about the network.
+#[async_trait]
+pub trait PeerClient: Send + Sync {
+    async fn apply(&self, node: NodeId, entry: ReplicatedEntry) -> anyhow::Result<()>;
+
+    async fn read(
+        &self,
+        node: NodeId,
+        table_name: Arc<String>,
+        key: Vec<u8>,
+    ) -> anyhow::Result<Option<VersionedValue>>;
+
+    /// Content hashes for anti-entropy: `(key, hash(value))` for every key in
+    /// `table_name`, ordered by key. Used to find what a lagging replica is missing
+    /// or has stale without shipping full values up front.
+    async fn content_hashes(
+        &self,
+        node: NodeId,
+        table_name: Arc<String>,
+    ) -> anyhow::Result<Vec<(Vec<u8>, u64)>>;
+}
+
+fn entry_hash(value: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Rendezvous (highest random weight) hash of `node` for `key`: stable per (node,
+/// key) pair, so the set of responsible nodes only changes for the keys that
+/// actually need to move when the ring changes, unlike a fixed-slot ring.
+fn rendezvous_weight(node: NodeId, key: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    node.hash(&mut hasher);
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Pick the freshest copy among a read-quorum's responses by highest `seq_id`,
+/// dropping nodes that errored or had nothing for the key.
+fn reconcile(results: impl IntoIterator<Item = Option<VersionedValue>>) -> Option<Vec<u8>> {
+    results
+        .into_iter()
+        .flatten()
+        .max_by_key(|v| v.seq_id)
+        .map(|v| v.value)
+}
+
+/// The `replication_factor` nodes responsible for `key`, highest weight first.
+pub fn responsible_nodes(ring: &[NodeId], key: &[u8], replication_factor: usize) -> Vec<NodeId> {
+    let mut scored: Vec<(u64, NodeId)> = ring
+        .iter()
+        .map(|&node| (rendezvous_weight(node, key), node))
+        .collect();
+    scored.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+    scored
+        .into_iter()
+        .take(replication_factor)
+        .map(|(_, node)| node)
+        .collect()
+}
+
+/// Coordinates quorum-replicated reads/writes for one table across the cluster,
+/// applying locally through the node's own `TableManage` actor and reaching peers
+/// through a [`PeerClient`].
+pub struct ReplicatedTableManage {
+    pub local_node: NodeId,
+    pub ring: Vec<NodeId>,
+    pub config: ReplicationConfig,
+    pub local: Addr<TableManage>,
+    pub peers: Arc<dyn PeerClient>,
+}
+
+impl ReplicatedTableManage {
+    pub fn new(
+        local_node: NodeId,
+        ring: Vec<NodeId>,
+        config: ReplicationConfig,
+        local: Addr<TableManage>,
+        peers: Arc<dyn PeerClient>,
+    ) -> Self {
+        Self {
+            local_node,
+            ring,
+            config,
+            local,
+            peers,
+        }
+    }
+
+    /// Write `key`/`value` to every node responsible for it. The mutation is sent to
+    /// all of them and this waits until every one has answered; it succeeds if at
+    /// least `write_quorum` of them acked. It does not return early once the quorum
+    /// is reached. The version used for reconciliation is the key's own per-key
+    /// version, bumped by one: *not* the table's shared id sequence
+    /// (`TableManageCmd::NextId`), which is a per-table auto-increment that may not
+    /// even be configured for the table being written.
+    ///
+    /// The bump and the local write are one `BumpVersioned` round-trip, not a
+    /// `GetVersioned` read followed by a separate `SetVersioned` write: two concurrent
+    /// `write()` calls for the same key could otherwise both read the same prior
+    /// version (a second message can be dispatched to the actor in between the two
+    /// round-trips) and both compute the same bumped `seq_id`, silently dropping one
+    /// of the writes. `BumpVersioned` does the read and the write inside a single
+    /// `Handler::handle` invocation, which `TableManage`'s mailbox never interleaves
+    /// with another message.
+    pub async fn write(
+        &self,
+        table_name: Arc<String>,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    ) -> anyhow::Result<()> {
+        let seq_id = match self
+            .local
+            .send(TableManageCmd::BumpVersioned {
+                table_name: table_name.clone(),
+                key: key.clone(),
+                value: value.clone(),
+            })
+            .await??
+        {
+            TableManageResult::Versioned { version, .. } => version,
+            _ => anyhow::bail!("BumpVersioned did not return a Versioned result"),
+        };
+
+        let responsible = responsible_nodes(&self.ring, &key, self.config.replication_factor);
+        let entry = ReplicatedEntry {
+            table_name: table_name.clone(),
+            key: key.clone(),
+            value: value.clone(),
+            seq_id,
+        };
+
+        let acks = join_all(responsible.iter().map(|&node| {
+            let entry = entry.clone();
+            async move {
+                if node == self.local_node {
+                    // Already applied above via `BumpVersioned`.
+                    Ok(())
+                } else {
+                    self.peers.apply(node, entry).await
+                }
+            }
+        }))
+        .await;
+
+        let ok_count = acks.iter().filter(|r| r.is_ok()).count();
+        if ok_count >= self.config.write_quorum {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "write quorum not met for table `{}`: {}/{} acked (need {})",
+                table_name,
+                ok_count,
+                responsible.len(),
+                self.config.write_quorum
+            ))
+        }
+    }
+
+    /// Read `key` from every node responsible for it and reconcile by taking the
+    /// response with the highest seq id, erroring out if fewer than `read_quorum` of
+    /// them actually responded. Querying only `read_quorum` candidates (rather than
+    /// all of `responsible`) would mean a single down or erroring node among that
+    /// small set silently drops the read below quorum instead of raising an error,
+    /// breaking the `write_quorum + read_quorum > replication_factor` overlap
+    /// guarantee the caller is relying on. A node answering with "no value for this
+    /// key" still counts as a response for quorum purposes; only a transport/actor
+    /// error does not.
+    pub async fn read(
+        &self,
+        table_name: Arc<String>,
+        key: Vec<u8>,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let responsible = responsible_nodes(&self.ring, &key, self.config.replication_factor);
+
+        let reads = join_all(responsible.iter().map(|&node| {
+            let table_name = table_name.clone();
+            let key = key.clone();
+            async move {
+                if node == self.local_node {
+                    match self
+                        .local
+                        .send(TableManageCmd::GetVersioned { table_name, key })
+                        .await??
+                    {
+                        TableManageResult::Versioned {
+                            value: Some(value),
+                            version,
+                        } => Ok(Some(VersionedValue {
+                            value,
+                            seq_id: version,
+                        })),
+                        _ => Ok(None),
+                    }
+                } else {
+                    self.peers.read(node, table_name, key).await
+                }
+            }
+        }))
+        .await;
+
+        let read_quorum = self.config.read_quorum.max(1);
+        let ok_count = reads.iter().filter(|r| r.is_ok()).count();
+        if ok_count < read_quorum {
+            return Err(anyhow::anyhow!(
+                "read quorum not met for table `{}`: {}/{} responded (need {})",
+                table_name,
+                ok_count,
+                responsible.len(),
+                read_quorum
+            ));
+        }
+
+        Ok(reconcile(reads.into_iter().map(|r| r.ok().flatten())))
+    }
+
+    /// Exchange per-key content hashes with `peer` for `table_name` and ship back
+    /// whatever it's missing or has a stale copy of, repairing a lagging replica
+    /// without re-shipping the whole table.
+    pub async fn anti_entropy_sync(
+        &self,
+        table_name: Arc<String>,
+        peer: NodeId,
+    ) -> anyhow::Result<usize> {
+        let local_hashes = self.local_content_hashes(table_name.clone()).await?;
+        let peer_hashes = self.peers.content_hashes(peer, table_name.clone()).await?;
+        let peer_hashes: std::collections::HashMap<Vec<u8>, u64> = peer_hashes.into_iter().collect();
+
+        let mut repaired = 0usize;
+        for (key, local_hash) in local_hashes {
+            if peer_hashes.get(&key) == Some(&local_hash) {
+                continue;
+            }
+            let Some((value, version)) = (match self
+                .local
+                .send(TableManageCmd::GetVersioned {
+                    table_name: table_name.clone(),
+                    key: key.clone(),
+                })
+                .await??
+            {
+                TableManageResult::Versioned {
+                    value: Some(v),
+                    version,
+                } => Some((v, version)),
+                _ => None,
+            }) else {
+                continue;
+            };
+            self.peers
+                .apply(
+                    peer,
+                    ReplicatedEntry {
+                        table_name: table_name.clone(),
+                        key,
+                        value,
+                        seq_id: version,
+                    },
+                )
+                .await?;
+            repaired += 1;
+        }
+        Ok(repaired)
+    }
+
+    async fn local_content_hashes(
+        &self,
+        table_name: Arc<String>,
+    ) -> anyhow::Result<Vec<(Vec<u8>, u64)>> {
+        let mut hashes = Vec::new();
+        let mut start = None;
+        loop {
+            let result = self
+                .local
+                .send(TableManageCmd::Range {
+                    table_name: table_name.clone(),
+                    start: start.clone(),
+                    end: None,
+                    limit: 1000,
+                    reverse: false,
+                })
+                .await??;
+            let TableManageResult::Range { items, next_start } = result else {
+                break;
+            };
+            if items.is_empty() {
+                break;
+            }
+            hashes.extend(items.iter().map(|(k, v)| (k.clone(), entry_hash(v))));
+            if next_start.is_none() {
+                break;
+            }
+            start = next_start;
+        }
+        Ok(hashes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reconcile_picks_the_highest_seq_id() {
+        let results = vec![
+            Some(VersionedValue {
+                value: b"stale".to_vec(),
+                seq_id: 3,
+            }),
+            None, // e.g. a node that errored or had nothing for the key
+            Some(VersionedValue {
+                value: b"fresh".to_vec(),
+                seq_id: 7,
+            }),
+        ];
+        assert_eq!(reconcile(results), Some(b"fresh".to_vec()));
+    }
+
+    #[test]
+    fn reconcile_of_all_misses_is_none() {
+        assert_eq!(reconcile(vec![None, None]), None);
+    }
+
+    #[test]
+    fn responsible_nodes_is_stable_for_the_same_ring_and_key() {
+        let ring = vec![1, 2, 3, 4, 5];
+        let a = responsible_nodes(&ring, b"some-key", 3);
+        let b = responsible_nodes(&ring, b"some-key", 3);
+        assert_eq!(a, b);
+        assert_eq!(a.len(), 3);
+    }
+}
@@ -1,10 +1,35 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use serde::{Deserialize, Serialize};
 
 use actix::prelude::*;
+use tokio::sync::Notify;
 
 use crate::common::sled_utils::TableSequence;
+use crate::metrics::Metrics;
+use crate::raft::db::kv_backend::KvBackend;
+
+/// Cheap content hash used by `Watch` to tell a client whether the value it already
+/// has is stale, without shipping the value itself on every poll.
+fn hash_value(value: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The smallest byte string strictly greater than `key` under byte-wise lexicographic
+/// order (what every `KvBackend` orders keys by). Used to turn the last key of a
+/// `range` page into an *exclusive* continuation bound instead of re-including it.
+fn key_successor(key: &[u8]) -> Vec<u8> {
+    let mut next = key.to_vec();
+    next.push(0);
+    next
+}
 
 #[derive(Clone, prost::Message, Serialize, Deserialize)]
 pub struct TableDefinition {
@@ -12,6 +37,13 @@ pub struct TableDefinition {
     pub name: String,
     #[prost(uint32, tag = "2")]
     pub sequence_step: u32, // 0: None
+    /// Reject inserts once the table holds this many items. `None` means unlimited.
+    #[prost(uint64, optional, tag = "3")]
+    pub max_items: Option<u64>,
+    /// Reject inserts once the table's key+value bytes would exceed this. `None` means
+    /// unlimited.
+    #[prost(uint64, optional, tag = "4")]
+    pub max_bytes: Option<u64>,
 }
 
 impl TableDefinition {
@@ -27,81 +59,211 @@ impl TableDefinition {
 }
 
 pub(crate) const TABLE_DEFINITION_TREE_NAME: &str = "tables";
+/// Tree holding one [`TableCounter`] per table, keyed by table name, so item/byte
+/// counts survive restarts without an O(n) `.len()` scan.
+pub(crate) const TABLE_COUNTER_TREE_NAME: &str = "table_counters";
+
+/// Number of items and total key+value bytes stored in a table, kept up to date on
+/// every insert/remove so it never needs a full scan to answer.
+#[derive(Clone, Copy, Default, prost::Message)]
+pub struct TableCounter {
+    #[prost(uint64, tag = "1")]
+    pub item_count: u64,
+    #[prost(uint64, tag = "2")]
+    pub byte_size: u64,
+}
+
+impl TableCounter {
+    fn to_bytes(self) -> Vec<u8> {
+        let mut v = Vec::new();
+        prost::Message::encode(&self, &mut v).unwrap();
+        v
+    }
+
+    fn from_bytes(v: &[u8]) -> anyhow::Result<Self> {
+        Ok(prost::Message::decode(v)?)
+    }
+}
 
 pub struct TableInfo {
     pub name: Arc<String>,
     pub table_db_name: String,
     pub seq: Option<TableSequence>,
+    pub counter: TableCounter,
+    pub max_items: Option<u64>,
+    pub max_bytes: Option<u64>,
 }
 
 impl TableInfo {
-    pub fn new(name: Arc<String>, db: Arc<sled::Db>, sequence_step: u32) -> Self {
+    pub fn new(name: Arc<String>, backend: Arc<dyn KvBackend>, definition: &TableDefinition) -> Self {
         let table_name = format!("t_{}", &name);
-        let seq = if sequence_step == 0 {
+        let seq = if definition.sequence_step == 0 {
             None
         } else {
             Some(TableSequence::new(
-                db,
+                backend.clone(),
                 format!("seq_{}", &name),
-                sequence_step as u64,
+                definition.sequence_step as u64,
             ))
         };
+        let counter = backend
+            .get(TABLE_COUNTER_TREE_NAME, name.as_bytes())
+            .ok()
+            .flatten()
+            .and_then(|v| TableCounter::from_bytes(&v).ok())
+            .unwrap_or_default();
         Self {
             name,
             table_db_name: table_name,
             seq,
+            counter,
+            max_items: definition.max_items,
+            max_bytes: definition.max_bytes,
         }
     }
 }
 
 pub struct TableManage {
-    pub db: Arc<sled::Db>,
+    pub backend: Arc<dyn KvBackend>,
     pub table_map: HashMap<Arc<String>, TableInfo>,
+    /// Parked `Watch` requests, keyed by the exact (table, key) they're waiting on.
+    /// Drained and woken from the insert/remove write path.
+    watchers: HashMap<(Arc<String>, Vec<u8>), Vec<Arc<Notify>>>,
+    metrics: Arc<Metrics>,
 }
 
 impl TableManage {
-    pub fn new(db: Arc<sled::Db>) -> Self {
+    pub fn new(backend: Arc<dyn KvBackend>, metrics: Arc<Metrics>) -> Self {
         let mut s = Self {
-            db,
+            backend,
             table_map: Default::default(),
+            watchers: Default::default(),
+            metrics,
         };
         s.load_tables();
         s
     }
 
-    /// load table info from db
+    /// Wake every `Watch` parked on `(name, key)`.
+    fn notify_watchers(&mut self, name: &Arc<String>, key: &[u8]) {
+        if let Some(parked) = self.watchers.remove(&(name.clone(), key.to_vec())) {
+            for notify in parked {
+                notify.notify_waiters();
+            }
+        }
+    }
+
+    /// Deregister `notify` from `(name, key)`'s parked list once its `Watch` future
+    /// resolves (by wake or by timeout), so a key that's watched repeatedly but
+    /// rarely written doesn't leak one `Notify` per poll cycle.
+    fn remove_watcher(&mut self, name: &Arc<String>, key: &[u8], notify: &Arc<Notify>) {
+        let watch_key = (name.clone(), key.to_vec());
+        if let Some(parked) = self.watchers.get_mut(&watch_key) {
+            parked.retain(|n| !Arc::ptr_eq(n, notify));
+            if parked.is_empty() {
+                self.watchers.remove(&watch_key);
+            }
+        }
+    }
+
+    /// load table info from the backend
     fn load_tables(&mut self) {
-        let tables = self.db.open_tree(TABLE_DEFINITION_TREE_NAME).unwrap();
-        let mut iter = tables.iter();
-        while let Some(Ok((_, v))) = iter.next() {
+        self.backend.open_tree(TABLE_DEFINITION_TREE_NAME).unwrap();
+        let iter = self.backend.iter(TABLE_DEFINITION_TREE_NAME).unwrap();
+        for (_, v) in iter {
             if let Ok(definition) = TableDefinition::from_bytes(v.as_ref()) {
                 let name = Arc::new(definition.name.to_owned());
                 self.table_map.insert(
                     name.clone(),
-                    TableInfo::new(name, self.db.clone(), definition.sequence_step),
+                    TableInfo::new(name, self.backend.clone(), &definition),
                 );
             }
         }
     }
 
     fn init_table(&mut self, name: Arc<String>, sequence_step: u32) {
-        let tables = self.db.open_tree(TABLE_DEFINITION_TREE_NAME).unwrap();
         let definition = TableDefinition {
             name: name.as_ref().to_owned(),
             sequence_step,
+            max_items: None,
+            max_bytes: None,
         };
-        tables
-            .insert(name.as_bytes(), definition.to_bytes())
+        self.backend
+            .insert(
+                TABLE_DEFINITION_TREE_NAME,
+                name.as_bytes(),
+                definition.to_bytes(),
+            )
             .unwrap();
     }
 
+    /// Set (or clear) the item/byte quota for a table, creating it if it doesn't
+    /// exist yet. Persisted alongside the table's other definition fields.
+    pub fn set_quota(&mut self, name: Arc<String>, max_items: Option<u64>, max_bytes: Option<u64>) {
+        let sequence_step = self
+            .backend
+            .get(TABLE_DEFINITION_TREE_NAME, name.as_bytes())
+            .ok()
+            .flatten()
+            .and_then(|v| TableDefinition::from_bytes(&v).ok())
+            .map(|d| d.sequence_step)
+            .unwrap_or(0);
+        let definition = TableDefinition {
+            name: name.as_ref().to_owned(),
+            sequence_step,
+            max_items,
+            max_bytes,
+        };
+        self.backend
+            .insert(
+                TABLE_DEFINITION_TREE_NAME,
+                name.as_bytes(),
+                definition.to_bytes(),
+            )
+            .unwrap();
+        if let Some(table_info) = self.table_map.get_mut(&name) {
+            table_info.max_items = max_items;
+            table_info.max_bytes = max_bytes;
+        } else {
+            self.table_map.insert(
+                name.clone(),
+                TableInfo::new(name, self.backend.clone(), &definition),
+            );
+        }
+    }
+
     pub fn drop_table(&mut self, name: &Arc<String>) {
         if let Some(mut table) = self.table_map.remove(name) {
             if let Some(seq) = table.seq.as_mut() {
                 seq.set_table_last_id(0).ok();
             }
-            self.db.drop_tree(&table.table_db_name).ok();
+            self.backend.drop_tree(&table.table_db_name).ok();
+            // Without this, recreating a table with the same name inherits whatever
+            // per-key versions the deleted table left behind in `ver_<name>`, so
+            // genuinely new writes can lose reconciliation to stale leftover versions.
+            self.backend.drop_tree(&Self::version_tree_name(name)).ok();
+            self.backend
+                .remove(TABLE_COUNTER_TREE_NAME, name.as_bytes())
+                .ok();
+        }
+    }
+
+    /// Rebuild a table's item/byte counter by scanning it, in case a prior write was
+    /// interrupted and left the counter desynced.
+    pub fn recount_table(&mut self, name: &Arc<String>) -> anyhow::Result<TableCounter> {
+        let table_info = self
+            .table_map
+            .get_mut(name)
+            .ok_or_else(|| anyhow::anyhow!("the table {} does not exist", name))?;
+        let mut counter = TableCounter::default();
+        for (key, value) in self.backend.iter(&table_info.table_db_name)? {
+            counter.item_count += 1;
+            counter.byte_size += (key.len() + value.len()) as u64;
         }
+        table_info.counter = counter;
+        self.backend
+            .insert(TABLE_COUNTER_TREE_NAME, name.as_bytes(), counter.to_bytes())?;
+        Ok(counter)
     }
 
     pub fn next_id(&mut self, name: Arc<String>, seq_step: u32) -> anyhow::Result<u64> {
@@ -113,13 +275,85 @@ impl TableManage {
             }
         } else {
             self.init_table(name.clone(), seq_step);
-            let mut table_info = TableInfo::new(name.clone(), self.db.clone(), 0);
-            let r = table_info.seq.as_mut().unwrap().next_id();
+            let definition = TableDefinition {
+                name: name.as_ref().to_owned(),
+                sequence_step: seq_step,
+                max_items: None,
+                max_bytes: None,
+            };
+            let mut table_info = TableInfo::new(name.clone(), self.backend.clone(), &definition);
+            let r = match table_info.seq.as_mut() {
+                Some(seq) => seq.next_id(),
+                None => Err(anyhow::anyhow!("the table {} seq is none", &name)),
+            };
             self.table_map.insert(name, table_info);
             r
         }
     }
 
+    /// Tree holding the per-key version `insert_versioned` tags writes with, used by
+    /// replication to reconcile diverging copies — distinct from the table's
+    /// `seq_{name}` sequence, which is a shared auto-increment id generator, not a
+    /// per-key version, and isn't guaranteed to exist for every table.
+    fn version_tree_name(name: &Arc<String>) -> String {
+        format!("ver_{}", name)
+    }
+
+    /// Insert `value` at `key` in `name`, tagging it with `version` (stored alongside
+    /// it in a dedicated per-table tree) instead of bumping the table's shared id
+    /// sequence. Used by [`crate::raft::db::replication::ReplicatedTableManage`],
+    /// where every mutation already carries the version it should land at.
+    pub fn insert_versioned(
+        &mut self,
+        name: Arc<String>,
+        key: Vec<u8>,
+        value: Vec<u8>,
+        version: u64,
+    ) -> anyhow::Result<()> {
+        self.insert(name.clone(), key.clone(), value, None)?;
+        let version_tree = Self::version_tree_name(&name);
+        self.backend.open_tree(&version_tree)?;
+        self.backend
+            .insert(&version_tree, &key, version.to_be_bytes().to_vec())?;
+        Ok(())
+    }
+
+    /// Current value and version at `key` in `name`, as written by `insert_versioned`.
+    /// A key that was only ever written through the plain `insert` path has no
+    /// tracked version and reads back as version `0`.
+    pub fn get_versioned(&self, name: &Arc<String>, key: &[u8]) -> anyhow::Result<Option<(Vec<u8>, u64)>> {
+        let Some(value) = self.get(name, key) else {
+            return Ok(None);
+        };
+        let version_tree = Self::version_tree_name(name);
+        let version = self
+            .backend
+            .get(&version_tree, key)?
+            .and_then(|v| v.try_into().ok())
+            .map(u64::from_be_bytes)
+            .unwrap_or(0);
+        Ok(Some((value, version)))
+    }
+
+    /// Read `key`'s current version, bump it by one, and store `value` at the bumped
+    /// version, returning it. Unlike calling `get_versioned` then `insert_versioned`
+    /// from outside the actor, this is one call into `TableManage`, so it can't
+    /// interleave with another writer's read-then-write of the same key the way two
+    /// separate `TableManageCmd`s sent over an `Addr` could: `TableManage`'s mailbox
+    /// runs one `Handler::handle` at a time, so the read and the write here are
+    /// effectively atomic with respect to every other command this actor processes.
+    pub fn bump_versioned(
+        &mut self,
+        name: Arc<String>,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    ) -> anyhow::Result<u64> {
+        let current_version = self.get_versioned(&name, &key)?.map(|(_, v)| v).unwrap_or(0);
+        let version = current_version + 1;
+        self.insert_versioned(name, key, value, version)?;
+        Ok(version)
+    }
+
     pub fn set_last_seq_id(&mut self, name: Arc<String>, last_seq_id: u64) {
         if let Some(table_info) = self.table_map.get_mut(&name) {
             if let Some(seq) = table_info.seq.as_mut() {
@@ -134,38 +368,248 @@ impl TableManage {
         key: K,
         value: Vec<u8>,
         last_seq_id: Option<u64>,
-    ) -> Option<sled::IVec>
+    ) -> anyhow::Result<Option<Vec<u8>>>
     where
         K: AsRef<[u8]>,
     {
-        if let Some(table_info) = self.table_map.get_mut(&name) {
-            if let (Some(seq), Some(last_seq_id)) = (table_info.seq.as_mut(), last_seq_id) {
-                seq.set_table_last_id(last_seq_id).ok();
-            }
-            let table = self.db.open_tree(&table_info.table_db_name).unwrap();
-            table.insert(key, value).unwrap()
-        } else {
+        if !self.table_map.contains_key(&name) {
             self.init_table(name.clone(), 0);
-            let mut table_info = TableInfo::new(name.clone(), self.db.clone(), 0);
-            if let (Some(seq), Some(last_seq_id)) = (table_info.seq.as_mut(), last_seq_id) {
-                seq.set_table_last_id(last_seq_id).ok();
+            let definition = TableDefinition {
+                name: name.as_ref().to_owned(),
+                sequence_step: 0,
+                max_items: None,
+                max_bytes: None,
+            };
+            let table_info = TableInfo::new(name.clone(), self.backend.clone(), &definition);
+            self.table_map.insert(name.clone(), table_info);
+        }
+        let table_info = self.table_map.get_mut(&name).unwrap();
+        if let (Some(seq), Some(last_seq_id)) = (table_info.seq.as_mut(), last_seq_id) {
+            seq.set_table_last_id(last_seq_id).ok();
+        }
+
+        let key = key.as_ref();
+        let old = self.backend.get(&table_info.table_db_name, key)?;
+        let is_new_key = old.is_none();
+        let old_len = old.as_ref().map_or(0, |v| (key.len() + v.len()) as u64);
+        let new_len = (key.len() + value.len()) as u64;
+
+        if is_new_key {
+            if let Some(max_items) = table_info.max_items {
+                if table_info.counter.item_count + 1 > max_items {
+                    return Err(anyhow::anyhow!(
+                        "table `{}` is at its item quota ({})",
+                        name,
+                        max_items
+                    ));
+                }
+            }
+        }
+        if let Some(max_bytes) = table_info.max_bytes {
+            let projected = table_info.counter.byte_size + new_len - old_len;
+            if projected > max_bytes {
+                return Err(anyhow::anyhow!(
+                    "table `{}` is at its byte quota ({})",
+                    name,
+                    max_bytes
+                ));
             }
-            let table = self.db.open_tree(&table_info.table_db_name).unwrap();
-            self.table_map.insert(name, table_info);
-            table.insert(key, value).unwrap()
         }
+
+        let prev = self.backend.insert(&table_info.table_db_name, key, value)?;
+        if is_new_key {
+            table_info.counter.item_count += 1;
+        }
+        table_info.counter.byte_size = table_info.counter.byte_size + new_len - old_len;
+        self.backend.insert(
+            TABLE_COUNTER_TREE_NAME,
+            name.as_bytes(),
+            table_info.counter.to_bytes(),
+        )?;
+        self.notify_watchers(&name, key);
+        Ok(prev)
     }
 
-    pub fn remove<K>(&mut self, name: Arc<String>, key: K) -> Option<sled::IVec>
+    pub fn remove<K>(&mut self, name: Arc<String>, key: K) -> anyhow::Result<Option<Vec<u8>>>
     where
         K: AsRef<[u8]>,
     {
-        if let Some(table_info) = self.table_map.get(&name) {
-            let table = self.db.open_tree(&table_info.table_db_name).unwrap();
-            table.remove(key).unwrap()
+        let Some(table_info) = self.table_map.get_mut(&name) else {
+            return Ok(None);
+        };
+        let key = key.as_ref();
+        let removed = self.backend.remove(&table_info.table_db_name, key)?;
+        if let Some(v) = &removed {
+            table_info.counter.item_count = table_info.counter.item_count.saturating_sub(1);
+            table_info.counter.byte_size = table_info
+                .counter
+                .byte_size
+                .saturating_sub((key.len() + v.len()) as u64);
+            self.backend.insert(
+                TABLE_COUNTER_TREE_NAME,
+                name.as_bytes(),
+                table_info.counter.to_bytes(),
+            )?;
+            self.notify_watchers(&name, key);
+        }
+        Ok(removed)
+    }
+
+    pub fn get<K>(&self, name: &Arc<String>, key: K) -> Option<Vec<u8>>
+    where
+        K: AsRef<[u8]>,
+    {
+        let table_info = self.table_map.get(name)?;
+        self.backend
+            .get(&table_info.table_db_name, key.as_ref())
+            .unwrap()
+    }
+
+    /// Apply many inserts/removes to one table atomically with respect to its item/
+    /// byte quota: the whole batch is simulated first (removes, then inserts, the
+    /// same order it's actually applied in, so a key present in both ends up
+    /// inserted) and rejected up front if the net result would break the quota,
+    /// instead of applying a prefix of it and failing partway through.
+    pub fn batch_write(
+        &mut self,
+        name: Arc<String>,
+        inserts: Vec<(Vec<u8>, Vec<u8>)>,
+        removes: Vec<Vec<u8>>,
+    ) -> anyhow::Result<()> {
+        if !self.table_map.contains_key(&name) {
+            self.init_table(name.clone(), 0);
+            let definition = TableDefinition {
+                name: name.as_ref().to_owned(),
+                sequence_step: 0,
+                max_items: None,
+                max_bytes: None,
+            };
+            let table_info = TableInfo::new(name.clone(), self.backend.clone(), &definition);
+            self.table_map.insert(name.clone(), table_info);
+        }
+
+        let (table_db_name, max_items, max_bytes, mut projected_items, mut projected_bytes) = {
+            let table_info = self.table_map.get(&name).unwrap();
+            (
+                table_info.table_db_name.clone(),
+                table_info.max_items,
+                table_info.max_bytes,
+                table_info.counter.item_count,
+                table_info.counter.byte_size,
+            )
+        };
+
+        // Simulated key -> current key+value byte length, `None` meaning absent.
+        // Seeded lazily from the backend so a key touched more than once in the same
+        // batch is tracked against its simulated state, not re-read from disk.
+        let mut sim: HashMap<Vec<u8>, Option<u64>> = HashMap::new();
+        for key in &removes {
+            let existing_len = match sim.get(key) {
+                Some(len) => *len,
+                None => self
+                    .backend
+                    .get(&table_db_name, key)?
+                    .map(|v| (key.len() + v.len()) as u64),
+            };
+            if let Some(len) = existing_len {
+                projected_items = projected_items.saturating_sub(1);
+                projected_bytes = projected_bytes.saturating_sub(len);
+            }
+            sim.insert(key.clone(), None);
+        }
+        for (key, value) in &inserts {
+            let old_len = match sim.get(key) {
+                Some(len) => *len,
+                None => self
+                    .backend
+                    .get(&table_db_name, key)?
+                    .map(|v| (key.len() + v.len()) as u64),
+            };
+            let new_len = (key.len() + value.len()) as u64;
+            if old_len.is_none() {
+                projected_items += 1;
+            }
+            projected_bytes = projected_bytes + new_len - old_len.unwrap_or(0);
+            sim.insert(key.clone(), Some(new_len));
+        }
+
+        if let Some(max_items) = max_items {
+            if projected_items > max_items {
+                return Err(anyhow::anyhow!(
+                    "table `{}` batch would exceed its item quota ({})",
+                    name,
+                    max_items
+                ));
+            }
+        }
+        if let Some(max_bytes) = max_bytes {
+            if projected_bytes > max_bytes {
+                return Err(anyhow::anyhow!(
+                    "table `{}` batch would exceed its byte quota ({})",
+                    name,
+                    max_bytes
+                ));
+            }
+        }
+
+        // Quota is already guaranteed to hold for the whole batch, so apply it for
+        // real through the same path a single write uses, with the per-write quota
+        // checks lifted meanwhile: an intermediate state of the batch can exceed the
+        // byte quota even though the final state does not.
+        let quota = {
+            let table_info = self.table_map.get_mut(&name).unwrap();
+            (table_info.max_items.take(), table_info.max_bytes.take())
+        };
+        let result = (|| -> anyhow::Result<()> {
+            for key in removes {
+                self.remove(name.clone(), key)?;
+            }
+            for (key, value) in inserts {
+                self.insert(name.clone(), key, value, None)?;
+            }
+            Ok(())
+        })();
+        if let Some(table_info) = self.table_map.get_mut(&name) {
+            table_info.max_items = quota.0;
+            table_info.max_bytes = quota.1;
+        }
+        result
+    }
+
+    /// Ordered scan of `name` between `start` (inclusive) and `end` (exclusive),
+    /// returning at most `limit` pairs plus a continuation key to pass as the next
+    /// call's `start`/`end` (depending on `reverse`) if the page was full.
+    pub fn range(
+        &self,
+        name: &Arc<String>,
+        start: Option<Vec<u8>>,
+        end: Option<Vec<u8>>,
+        limit: usize,
+        reverse: bool,
+    ) -> (Vec<(Vec<u8>, Vec<u8>)>, Option<Vec<u8>>) {
+        let Some(table_info) = self.table_map.get(name) else {
+            return (Vec::new(), None);
+        };
+        let items = self
+            .backend
+            .range(
+                &table_info.table_db_name,
+                start.as_deref(),
+                end.as_deref(),
+                limit,
+                reverse,
+            )
+            .unwrap_or_default();
+        // `start` is inclusive and `end` exclusive, so the next page's boundary must
+        // exclude the last key we already returned: for a forward scan that's its
+        // successor (handed back as the next call's `start`); for a reverse scan the
+        // last key itself is already correct as the next call's exclusive `end`.
+        let next_start = if items.len() == limit {
+            items.last().map(|(k, _)| if reverse { k.clone() } else { key_successor(k) })
         } else {
             None
-        }
+        };
+        (items, next_start)
     }
 }
 
@@ -186,6 +630,15 @@ pub enum TableManageAsyncCmd {
         key: Vec<u8>,
     },
     Drop(Arc<String>),
+    /// Long-poll a single key: if its current content hash differs from
+    /// `known_hash`, answer immediately; otherwise park until a write to `key`
+    /// wakes it or `timeout` elapses.
+    Watch {
+        table_name: Arc<String>,
+        key: Vec<u8>,
+        known_hash: Option<u64>,
+        timeout: Duration,
+    },
 }
 
 #[derive(Message)]
@@ -210,19 +663,150 @@ pub enum TableManageCmd {
         table_name: Arc<String>,
         last_seq_id: u64,
     },
+    Get {
+        table_name: Arc<String>,
+        key: Vec<u8>,
+    },
+    /// Apply many inserts/removes to one table. The item/byte quota is checked for
+    /// the whole batch before anything is written; the writes themselves are applied
+    /// one at a time, so a storage error partway through leaves the earlier ones in
+    /// place.
+    BatchWrite {
+        table_name: Arc<String>,
+        inserts: Vec<(Vec<u8>, Vec<u8>)>,
+        removes: Vec<Vec<u8>>,
+    },
+    /// Ordered key range scan, K2V-style: `start` inclusive, `end` exclusive, capped
+    /// at `limit` pairs. `reverse` walks backwards from `end`.
+    Range {
+        table_name: Arc<String>,
+        start: Option<Vec<u8>>,
+        end: Option<Vec<u8>>,
+        limit: usize,
+        reverse: bool,
+    },
+    /// Set (or clear) a table's item/byte quota.
+    SetQuota {
+        table_name: Arc<String>,
+        max_items: Option<u64>,
+        max_bytes: Option<u64>,
+    },
+    /// Rebuild a table's item/byte counter by scanning it, in case an interrupted
+    /// write left the counter desynced.
+    Recount { table_name: Arc<String> },
+    /// Insert `value` at `key`, tagged with an explicit per-key `version` rather than
+    /// the table's shared id sequence. Used to apply a replicated write.
+    SetVersioned {
+        table_name: Arc<String>,
+        key: Vec<u8>,
+        value: Vec<u8>,
+        version: u64,
+    },
+    /// Read `key`'s current value and version, as written by `SetVersioned`.
+    GetVersioned {
+        table_name: Arc<String>,
+        key: Vec<u8>,
+    },
+    /// Read-bump-write `key`'s version and store `value` at it, in one actor call so
+    /// concurrent callers can't race each other's read of the prior version. Used by
+    /// [`crate::raft::db::replication::ReplicatedTableManage::write`] instead of a
+    /// separate `GetVersioned` + `SetVersioned` pair.
+    BumpVersioned {
+        table_name: Arc<String>,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    },
 }
 
 pub enum TableManageResult {
     None,
     Value(Vec<u8>),
     NextId(u64),
+    /// A page of a `Range` scan, plus a continuation key to resume from if the page
+    /// was full (`None` means the scan reached `end`/`start`).
+    Range {
+        items: Vec<(Vec<u8>, Vec<u8>)>,
+        next_start: Option<Vec<u8>>,
+    },
+    Counter(TableCounter),
+    /// Current value (if any) and its content hash, returned by `Watch` once the
+    /// value changed or the poll timed out.
+    Watch {
+        value: Option<Vec<u8>>,
+        hash: u64,
+    },
+    /// Value and per-key version, returned by `GetVersioned` and `BumpVersioned`
+    /// (version `0` if the key doesn't exist or was never written through
+    /// `SetVersioned`/`BumpVersioned`).
+    Versioned {
+        value: Option<Vec<u8>>,
+        version: u64,
+    },
 }
 
 impl Handler<TableManageAsyncCmd> for TableManage {
     type Result = ResponseActFuture<Self, anyhow::Result<TableManageResult>>;
 
-    fn handle(&mut self, msg: TableManageAsyncCmd, ctx: &mut Self::Context) -> Self::Result {
-        todo!()
+    fn handle(&mut self, msg: TableManageAsyncCmd, _ctx: &mut Self::Context) -> Self::Result {
+        match msg {
+            TableManageAsyncCmd::Insert {
+                table_name,
+                key,
+                value,
+            } => {
+                let result = self.insert(table_name, key, value, None).map(|v| match v {
+                    Some(v) => TableManageResult::Value(v),
+                    None => TableManageResult::None,
+                });
+                Box::pin(actix::fut::ready(result)) as ResponseActFuture<Self, _>
+            }
+            TableManageAsyncCmd::Remove { table_name, key } => {
+                let result = self.remove(table_name, key).map(|v| match v {
+                    Some(v) => TableManageResult::Value(v),
+                    None => TableManageResult::None,
+                });
+                Box::pin(actix::fut::ready(result)) as ResponseActFuture<Self, _>
+            }
+            TableManageAsyncCmd::Drop(name) => {
+                self.drop_table(&name);
+                Box::pin(actix::fut::ready(Ok(TableManageResult::None)))
+                    as ResponseActFuture<Self, _>
+            }
+            TableManageAsyncCmd::Watch {
+                table_name,
+                key,
+                known_hash,
+                timeout,
+            } => {
+                let current = self.get(&table_name, key.clone());
+                let current_hash = current.as_ref().map(|v| hash_value(v));
+                if current_hash != known_hash {
+                    let result = Ok(TableManageResult::Watch {
+                        value: current,
+                        hash: current_hash.unwrap_or(0),
+                    });
+                    return Box::pin(actix::fut::ready(result)) as ResponseActFuture<Self, _>;
+                }
+
+                let notify = Arc::new(Notify::new());
+                self.watchers
+                    .entry((table_name.clone(), key.clone()))
+                    .or_default()
+                    .push(notify.clone());
+                let cleanup_notify = notify.clone();
+                let wait = async move {
+                    let _ = tokio::time::timeout(timeout, notify.notified()).await;
+                };
+                Box::pin(actix::fut::wrap_future::<_, Self>(wait).map(
+                    move |_, act: &mut Self, _ctx| {
+                        act.remove_watcher(&table_name, &key, &cleanup_notify);
+                        let value = act.get(&table_name, key.clone());
+                        let hash = value.as_ref().map(|v| hash_value(v)).unwrap_or(0);
+                        Ok(TableManageResult::Watch { value, hash })
+                    },
+                )) as ResponseActFuture<Self, _>
+            }
+        }
     }
 }
 
@@ -236,25 +820,47 @@ impl Handler<TableManageCmd> for TableManage {
                 key,
                 value,
                 last_seq_id,
-            } => match self.insert(table_name, key, value, last_seq_id) {
-                Some(v) => Ok(TableManageResult::Value(v.to_vec())),
-                None => Ok(TableManageResult::None),
-            },
-            TableManageCmd::Remove { table_name, key } => match self.remove(table_name, key) {
-                Some(v) => Ok(TableManageResult::Value(v.to_vec())),
-                None => Ok(TableManageResult::None),
-            },
+            } => {
+                let start = Instant::now();
+                let metrics = self.metrics.clone();
+                let result = self
+                    .insert(table_name.clone(), key, value, last_seq_id)
+                    .map(|v| match v {
+                        Some(v) => TableManageResult::Value(v),
+                        None => TableManageResult::None,
+                    });
+                metrics.record_table_op("insert", &table_name, start.elapsed());
+                result
+            }
+            TableManageCmd::Remove { table_name, key } => {
+                let start = Instant::now();
+                let metrics = self.metrics.clone();
+                let result = self.remove(table_name.clone(), key).map(|v| match v {
+                    Some(v) => TableManageResult::Value(v),
+                    None => TableManageResult::None,
+                });
+                metrics.record_table_op("remove", &table_name, start.elapsed());
+                result
+            }
             TableManageCmd::Drop(name) => {
+                let start = Instant::now();
                 self.drop_table(&name);
+                self.metrics.record_table_op("drop", &name, start.elapsed());
                 Ok(TableManageResult::None)
             }
             TableManageCmd::NextId {
                 table_name,
                 seq_step,
-            } => match self.next_id(table_name, seq_step.unwrap_or(100)) {
-                Ok(v) => Ok(TableManageResult::NextId(v)),
-                Err(_) => Ok(TableManageResult::None),
-            },
+            } => {
+                let start = Instant::now();
+                let result = match self.next_id(table_name.clone(), seq_step.unwrap_or(100)) {
+                    Ok(v) => Ok(TableManageResult::NextId(v)),
+                    Err(_) => Ok(TableManageResult::None),
+                };
+                self.metrics
+                    .record_table_op("next_id", &table_name, start.elapsed());
+                result
+            }
             TableManageCmd::SetSeqId {
                 table_name,
                 last_seq_id,
@@ -262,6 +868,324 @@ impl Handler<TableManageCmd> for TableManage {
                 self.set_last_seq_id(table_name, last_seq_id);
                 Ok(TableManageResult::None)
             }
+            TableManageCmd::Get { table_name, key } => match self.get(&table_name, key) {
+                Some(v) => Ok(TableManageResult::Value(v)),
+                None => Ok(TableManageResult::None),
+            },
+            TableManageCmd::BatchWrite {
+                table_name,
+                inserts,
+                removes,
+            } => {
+                self.batch_write(table_name, inserts, removes)?;
+                Ok(TableManageResult::None)
+            }
+            TableManageCmd::Range {
+                table_name,
+                start,
+                end,
+                limit,
+                reverse,
+            } => {
+                let (items, next_start) = self.range(&table_name, start, end, limit, reverse);
+                Ok(TableManageResult::Range { items, next_start })
+            }
+            TableManageCmd::SetQuota {
+                table_name,
+                max_items,
+                max_bytes,
+            } => {
+                self.set_quota(table_name, max_items, max_bytes);
+                Ok(TableManageResult::None)
+            }
+            TableManageCmd::Recount { table_name } => {
+                let counter = self.recount_table(&table_name)?;
+                Ok(TableManageResult::Counter(counter))
+            }
+            TableManageCmd::SetVersioned {
+                table_name,
+                key,
+                value,
+                version,
+            } => {
+                self.insert_versioned(table_name, key, value, version)?;
+                Ok(TableManageResult::None)
+            }
+            TableManageCmd::GetVersioned { table_name, key } => {
+                match self.get_versioned(&table_name, &key)? {
+                    Some((value, version)) => Ok(TableManageResult::Versioned {
+                        value: Some(value),
+                        version,
+                    }),
+                    None => Ok(TableManageResult::Versioned {
+                        value: None,
+                        version: 0,
+                    }),
+                }
+            }
+            TableManageCmd::BumpVersioned {
+                table_name,
+                key,
+                value,
+            } => {
+                let version = self.bump_versioned(table_name, key, value.clone())?;
+                Ok(TableManageResult::Versioned {
+                    value: Some(value),
+                    version,
+                })
+            }
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, sync::Mutex};
+
+    use super::*;
+
+    /// In-memory [`KvBackend`] used only by these tests, so they don't need a real
+    /// sled/sqlite/lmdb file on disk.
+    #[derive(Default)]
+    struct MemoryBackend {
+        trees: Mutex<HashMap<String, BTreeMap<Vec<u8>, Vec<u8>>>>,
+    }
+
+    impl KvBackend for MemoryBackend {
+        fn open_tree(&self, tree: &str) -> anyhow::Result<()> {
+            self.trees.lock().unwrap().entry(tree.to_owned()).or_default();
+            Ok(())
+        }
+
+        fn insert(&self, tree: &str, key: &[u8], value: Vec<u8>) -> anyhow::Result<Option<Vec<u8>>> {
+            Ok(self
+                .trees
+                .lock()
+                .unwrap()
+                .entry(tree.to_owned())
+                .or_default()
+                .insert(key.to_vec(), value))
+        }
+
+        fn remove(&self, tree: &str, key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+            Ok(self
+                .trees
+                .lock()
+                .unwrap()
+                .entry(tree.to_owned())
+                .or_default()
+                .remove(key))
+        }
+
+        fn get(&self, tree: &str, key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+            Ok(self
+                .trees
+                .lock()
+                .unwrap()
+                .entry(tree.to_owned())
+                .or_default()
+                .get(key)
+                .cloned())
+        }
+
+        fn iter(&self, tree: &str) -> anyhow::Result<Box<dyn Iterator<Item = (Vec<u8>, Vec<u8>)>>> {
+            let items: Vec<_> = self
+                .trees
+                .lock()
+                .unwrap()
+                .entry(tree.to_owned())
+                .or_default()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
+            Ok(Box::new(items.into_iter()))
+        }
+
+        fn drop_tree(&self, tree: &str) -> anyhow::Result<()> {
+            self.trees.lock().unwrap().remove(tree);
+            Ok(())
+        }
+
+        fn update(
+            &self,
+            tree: &str,
+            key: &[u8],
+            f: Box<dyn Fn(Option<&[u8]>) -> Option<Vec<u8>> + Send + Sync>,
+        ) -> anyhow::Result<Option<Vec<u8>>> {
+            let mut trees = self.trees.lock().unwrap();
+            let map = trees.entry(tree.to_owned()).or_default();
+            let new_value = f(map.get(key).map(|v| v.as_slice()));
+            match &new_value {
+                Some(v) => {
+                    map.insert(key.to_vec(), v.clone());
+                }
+                None => {
+                    map.remove(key);
+                }
+            }
+            Ok(new_value)
+        }
+
+        fn range(
+            &self,
+            tree: &str,
+            start: Option<&[u8]>,
+            end: Option<&[u8]>,
+            limit: usize,
+            reverse: bool,
+        ) -> anyhow::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+            use std::ops::Bound;
+            let trees = self.trees.lock().unwrap();
+            let Some(map) = trees.get(tree) else {
+                return Ok(Vec::new());
+            };
+            let bounds = (
+                start.map(|s| Bound::Included(s.to_vec())).unwrap_or(Bound::Unbounded),
+                end.map(|e| Bound::Excluded(e.to_vec())).unwrap_or(Bound::Unbounded),
+            );
+            let range = map.range::<Vec<u8>, _>(bounds);
+            let items = if reverse {
+                range
+                    .rev()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .take(limit)
+                    .collect()
+            } else {
+                range.map(|(k, v)| (k.clone(), v.clone())).take(limit).collect()
+            };
+            Ok(items)
+        }
+    }
+
+    fn table_manage() -> TableManage {
+        TableManage::new(Arc::new(MemoryBackend::default()), Arc::new(Metrics::new()))
+    }
+
+    #[test]
+    fn range_pages_do_not_duplicate_the_boundary_key() {
+        let mut tm = table_manage();
+        let name = Arc::new("t".to_owned());
+        for i in 0u8..10 {
+            tm.insert(name.clone(), vec![i], vec![i], None).unwrap();
+        }
+
+        let mut seen = Vec::new();
+        let mut start = None;
+        loop {
+            let (items, next_start) = tm.range(&name, start, None, 3, false);
+            seen.extend(items.iter().map(|(k, _)| k.clone()));
+            match next_start {
+                Some(next) => start = Some(next),
+                None => break,
+            }
+        }
+
+        let expected: Vec<Vec<u8>> = (0u8..10).map(|i| vec![i]).collect();
+        assert_eq!(seen, expected, "paginated range must visit every key exactly once");
+    }
+
+    #[test]
+    fn batch_write_over_quota_is_rejected_without_partial_apply() {
+        let mut tm = table_manage();
+        let name = Arc::new("t".to_owned());
+        tm.insert(name.clone(), b"existing".to_vec(), b"v".to_vec(), None)
+            .unwrap();
+        tm.set_quota(name.clone(), Some(1), None);
+
+        let err = tm
+            .batch_write(
+                name.clone(),
+                vec![(b"a".to_vec(), b"1".to_vec()), (b"b".to_vec(), b"2".to_vec())],
+                vec![],
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("item quota"));
+
+        // Neither of the two inserts should have been applied.
+        assert_eq!(tm.get(&name, b"a"), None);
+        assert_eq!(tm.get(&name, b"b"), None);
+        assert_eq!(tm.table_map.get(&name).unwrap().counter.item_count, 1);
+    }
+
+    #[test]
+    fn insert_is_rejected_once_the_item_quota_is_reached() {
+        let mut tm = table_manage();
+        let name = Arc::new("t".to_owned());
+        tm.set_quota(name.clone(), Some(1), None);
+
+        tm.insert(name.clone(), b"a".to_vec(), b"1".to_vec(), None)
+            .unwrap();
+        let err = tm
+            .insert(name.clone(), b"b".to_vec(), b"2".to_vec(), None)
+            .unwrap_err();
+        assert!(err.to_string().contains("item quota"));
+        assert_eq!(tm.table_map.get(&name).unwrap().counter.item_count, 1);
+
+        // Overwriting an existing key isn't a new item, so it must still be allowed
+        // at the quota.
+        tm.insert(name.clone(), b"a".to_vec(), b"11".to_vec(), None)
+            .unwrap();
+        assert_eq!(tm.table_map.get(&name).unwrap().counter.item_count, 1);
+    }
+
+    #[test]
+    fn bump_versioned_increments_on_each_call() {
+        let mut tm = table_manage();
+        let name = Arc::new("t".to_owned());
+
+        let v1 = tm
+            .bump_versioned(name.clone(), b"k".to_vec(), b"1".to_vec())
+            .unwrap();
+        let v2 = tm
+            .bump_versioned(name.clone(), b"k".to_vec(), b"2".to_vec())
+            .unwrap();
+        assert_eq!(v1, 1);
+        assert_eq!(v2, 2);
+        assert_eq!(
+            tm.get_versioned(&name, b"k").unwrap(),
+            Some((b"2".to_vec(), 2))
+        );
+    }
+
+    #[test]
+    fn drop_table_clears_stale_versions_for_a_recreated_table() {
+        let mut tm = table_manage();
+        let name = Arc::new("t".to_owned());
+        tm.bump_versioned(name.clone(), b"k".to_vec(), b"old".to_vec())
+            .unwrap();
+
+        tm.drop_table(&name);
+
+        let v1 = tm
+            .bump_versioned(name.clone(), b"k".to_vec(), b"new".to_vec())
+            .unwrap();
+        assert_eq!(
+            v1, 1,
+            "a recreated table must not inherit the deleted table's per-key versions"
+        );
+    }
+
+    #[test]
+    fn watch_cleanup_removes_only_this_waiter() {
+        let mut tm = table_manage();
+        let name = Arc::new("t".to_owned());
+        let notify_a = Arc::new(Notify::new());
+        let notify_b = Arc::new(Notify::new());
+        tm.watchers
+            .entry((name.clone(), b"k1".to_vec()))
+            .or_default()
+            .push(notify_a.clone());
+        tm.watchers
+            .entry((name.clone(), b"k1".to_vec()))
+            .or_default()
+            .push(notify_b.clone());
+
+        tm.remove_watcher(&name, b"k1", &notify_a);
+        let remaining = tm.watchers.get(&(name.clone(), b"k1".to_vec())).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert!(Arc::ptr_eq(&remaining[0], &notify_b));
+
+        tm.remove_watcher(&name, b"k1", &notify_b);
+        assert!(tm.watchers.get(&(name.clone(), b"k1".to_vec())).is_none());
+    }
 }
\ No newline at end of file